@@ -0,0 +1,203 @@
+// decouples copy_with_dir/mirror/FileIndex::handle_event from std::fs/tokio::fs
+// so the index/mirror logic can be driven by something other than a real disk,
+// loosely modeled on zed's Fs trait.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FsMetadata {
+    pub modified: SystemTime,
+    pub is_dir: bool,
+}
+
+#[async_trait]
+pub trait Fs: Send + Sync {
+    async fn create_dir_all(&self, path: &Path) -> std::io::Result<()>;
+    async fn copy_file(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+    async fn remove_file(&self, path: &Path) -> std::io::Result<()>;
+    async fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+    // Ok(None) means "not found", matching the exists()-then-metadata checks
+    // this replaces
+    async fn metadata(&self, path: &Path) -> std::io::Result<Option<FsMetadata>>;
+}
+
+pub struct RealFs;
+
+#[async_trait]
+impl Fs for RealFs {
+    async fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(path).await
+    }
+
+    async fn copy_file(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        tokio::fs::copy(from, to).await.map(|_| ())
+    }
+
+    async fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+        tokio::fs::remove_file(path).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        tokio::fs::rename(from, to).await
+    }
+
+    async fn metadata(&self, path: &Path) -> std::io::Result<Option<FsMetadata>> {
+        match tokio::fs::metadata(path).await {
+            Ok(md) => Ok(Some(FsMetadata {
+                modified: md.modified()?,
+                is_dir: md.is_dir(),
+            })),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+struct FakeEntry {
+    data: Vec<u8>,
+    modified: SystemTime,
+    is_dir: bool,
+}
+
+// in-memory backend so the index/mirror logic can be exercised deterministically
+// without touching disk
+pub struct FakeFs {
+    files: Mutex<HashMap<PathBuf, FakeEntry>>,
+}
+
+impl FakeFs {
+    pub fn new() -> Self {
+        Self {
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn seed_file(&self, path: impl Into<PathBuf>, data: Vec<u8>, modified: SystemTime) {
+        self.files.lock().unwrap().insert(
+            path.into(),
+            FakeEntry {
+                data,
+                modified,
+                is_dir: false,
+            },
+        );
+    }
+
+    fn not_found(path: &Path) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} not found", path.display()),
+        )
+    }
+}
+
+#[async_trait]
+impl Fs for FakeFs {
+    async fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        self.files.lock().unwrap().insert(
+            path.to_path_buf(),
+            FakeEntry {
+                data: Vec::new(),
+                modified: SystemTime::now(),
+                is_dir: true,
+            },
+        );
+        Ok(())
+    }
+
+    async fn copy_file(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let data = match files.get(from) {
+            Some(entry) => entry.data.clone(),
+            None => return Err(Self::not_found(from)),
+        };
+        if let Some(parent) = to.parent() {
+            if !parent.as_os_str().is_empty() && !files.get(parent).map_or(false, |e| e.is_dir) {
+                return Err(Self::not_found(parent));
+            }
+        }
+        files.insert(
+            to.to_path_buf(),
+            FakeEntry {
+                data,
+                modified: SystemTime::now(),
+                is_dir: false,
+            },
+        );
+        Ok(())
+    }
+
+    async fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+        match self.files.lock().unwrap().remove(path) {
+            Some(_) => Ok(()),
+            None => Err(Self::not_found(path)),
+        }
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        match files.remove(from) {
+            Some(entry) => {
+                files.insert(to.to_path_buf(), entry);
+                Ok(())
+            }
+            None => Err(Self::not_found(from)),
+        }
+    }
+
+    async fn metadata(&self, path: &Path) -> std::io::Result<Option<FsMetadata>> {
+        Ok(self.files.lock().unwrap().get(path).map(|e| FsMetadata {
+            modified: e.modified,
+            is_dir: e.is_dir,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn copy_with_dir_creates_missing_target_dir() {
+        let fs = FakeFs::new();
+        fs.seed_file("INBOX/a.txt", b"hello".to_vec(), SystemTime::now());
+
+        crate::copy_with_dir(&fs, Path::new("INBOX/a.txt"), Path::new("CLONE/a.txt"))
+            .await
+            .unwrap();
+
+        let md = fs.metadata(Path::new("CLONE/a.txt")).await.unwrap();
+        assert!(md.is_some());
+        let dir = fs.metadata(Path::new("CLONE")).await.unwrap();
+        assert!(matches!(dir, Some(md) if md.is_dir));
+    }
+
+    #[tokio::test]
+    async fn copy_with_dir_does_not_leave_tmp_file_behind() {
+        let fs = FakeFs::new();
+        fs.seed_file("INBOX/a.txt", b"hello".to_vec(), SystemTime::now());
+
+        crate::copy_with_dir(&fs, Path::new("INBOX/a.txt"), Path::new("CLONE/a.txt"))
+            .await
+            .unwrap();
+
+        let files = fs.files.lock().unwrap();
+        assert!(files.keys().all(|p| !p.to_string_lossy().contains(".fm-tmp")));
+    }
+
+    #[tokio::test]
+    async fn copy_with_dir_missing_source_errors_without_writing_target() {
+        let fs = FakeFs::new();
+
+        let err = crate::copy_with_dir(&fs, Path::new("INBOX/missing.txt"), Path::new("CLONE/missing.txt"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(fs.metadata(Path::new("CLONE/missing.txt")).await.unwrap().is_none());
+    }
+}
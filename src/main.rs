@@ -6,18 +6,26 @@ use std::fs::{read_dir, DirEntry};
 use std::panic::Location;
 use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::time::SystemTime;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 use chrono::{DateTime, Local};
 use futures::future::OptionFuture;
 use futures::stream::{StreamExt, FuturesUnordered};
-use notify::{Event, EventKind, RecursiveMode, Watcher};
-use notify::event::{RemoveKind, CreateKind};
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
 use tokio::{fs, select, signal};
 
+mod backend;
+mod ssh;
+
+use backend::Fs as _;
+
 const DEFAULT_INBOX: &'static str = "INBOX/";
 const DEFAULT_TARGET: &'static str = "CLONE/";
 const DEFAULT_LOG: &'static str = "fm.log";
+const DEFAULT_DEBOUNCE_MS: u64 = 50;
+const DEFAULT_MAX_WORKERS: usize = 4096;
 
 struct Args {
     contents: Vec<String>,
@@ -75,34 +83,157 @@ impl Logs {
 
 
 fn usage() {
-    println!("Usage: fm [OPTION] [ARGUMENT]");
+    println!("Usage: fm [-d DIRECTORY] [-r user@host:/path] [-w DEBOUNCE_MS] [-p POLL_MS] [-c MAX_WORKERS]");
 }
 
+// looks for `-d <dir>` anywhere in argv, same as init_remote/init_debounce/etc -
+// skips over the other flags' value tokens so e.g. `-w 100 -d custom_inbox`
+// finds `-d` regardless of where it sits
 fn init_inbox() -> String {
     let mut args = Args::new().into_iter();
     args.next(); // strip program name
 
-    let directory = match args.next() {
-        Some(opt) if opt == "-d" => {
-            if let Some(dir) = args.next() {
-                dir
-            } else {
+    let mut directory = String::from(DEFAULT_INBOX);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-d" => {
+                directory = match args.next() {
+                    Some(dir) => dir,
+                    None => {
+                        usage();
+                        exit(1);
+                    }
+                };
+            }
+            "-r" | "-w" | "-p" | "-c" => {
+                args.next(); // value belongs to that flag, read elsewhere
+            }
+            _ => {
                 usage();
                 exit(1);
             }
         }
-        Some(_) => {
-            usage();
-            exit(1);
-        }
-        None => String::from(DEFAULT_INBOX),
-    };
+    }
 
     if let Ok(false) = std::fs::exists(&directory) {
         std::fs::create_dir(&directory).unwrap();
     }
 
-    return directory;
+    directory
+}
+
+// looks for `-r user@host:/path` anywhere in argv and parses it into a remote
+// mirror target. None means mirror locally into CLONE/ like today.
+fn init_remote() -> Option<ssh::Target> {
+    let mut args = Args::new().into_iter();
+    args.next(); // strip program name
+
+    while let Some(arg) = args.next() {
+        if arg == "-r" {
+            let spec = match args.next() {
+                Some(spec) => spec,
+                None => {
+                    usage();
+                    exit(1);
+                }
+            };
+            return match ssh::parse_target(&spec) {
+                Some(target) => Some(target),
+                None => {
+                    eprintln!("ERROR: invalid remote target '{spec}', expected user@host:/path");
+                    exit(1);
+                }
+            };
+        }
+    }
+
+    None
+}
+
+// looks for `-w <ms>` anywhere in argv; how long a path has to sit quiet
+// before its buffered event gets flushed to mirror()
+fn init_debounce() -> Duration {
+    let mut args = Args::new().into_iter();
+    args.next(); // strip program name
+
+    while let Some(arg) = args.next() {
+        if arg == "-w" {
+            let ms = match args.next() {
+                Some(ms) => ms,
+                None => {
+                    usage();
+                    exit(1);
+                }
+            };
+            let ms: u64 = ms.parse().unwrap_or_else(|_| {
+                eprintln!("ERROR: invalid debounce window '{ms}', expected a number of milliseconds");
+                exit(1);
+            });
+            return Duration::from_millis(ms);
+        }
+    }
+
+    Duration::from_millis(DEFAULT_DEBOUNCE_MS)
+}
+
+// which notify backend to watch the inbox with
+enum WatcherKind {
+    // inotify/FSEvents/etc, whatever notify::recommended_watcher picks
+    Native,
+    // polls on an interval instead - needed on network filesystems/containers
+    // where the native backend doesn't deliver events
+    Poll(Duration),
+}
+
+// looks for `-p <ms>` anywhere in argv to switch to a poll-based watcher
+fn init_watcher_kind() -> WatcherKind {
+    let mut args = Args::new().into_iter();
+    args.next(); // strip program name
+
+    while let Some(arg) = args.next() {
+        if arg == "-p" {
+            let ms = match args.next() {
+                Some(ms) => ms,
+                None => {
+                    usage();
+                    exit(1);
+                }
+            };
+            let ms: u64 = ms.parse().unwrap_or_else(|_| {
+                eprintln!("ERROR: invalid poll interval '{ms}', expected a number of milliseconds");
+                exit(1);
+            });
+            return WatcherKind::Poll(Duration::from_millis(ms));
+        }
+    }
+
+    WatcherKind::Native
+}
+
+// looks for `-c <n>` anywhere in argv; caps how many mirror() calls can be
+// in flight at once across the initial bulk clone and the live event loop
+fn init_max_workers() -> usize {
+    let mut args = Args::new().into_iter();
+    args.next(); // strip program name
+
+    while let Some(arg) = args.next() {
+        if arg == "-c" {
+            let n = match args.next() {
+                Some(n) => n,
+                None => {
+                    usage();
+                    exit(1);
+                }
+            };
+            return n.parse().unwrap_or_else(|_| {
+                eprintln!("ERROR: invalid worker count '{n}', expected a number");
+                exit(1);
+            });
+        }
+    }
+
+    DEFAULT_MAX_WORKERS
 }
 
 fn find_files_in(path: &Path) -> Option<Vec<String>> {
@@ -140,13 +271,24 @@ fn find_files_in(path: &Path) -> Option<Vec<String>> {
     }
 }
 
+// what FileIndex::handle_event wants replayed onto the mirror target
+enum MirrorOp {
+    // create/modify: copy the host file over
+    Sync(PathBuf),
+    // remove from the mirror
+    Remove(PathBuf),
+    // move within the mirror instead of a delete+recopy
+    Rename { from: PathBuf, to: PathBuf },
+}
+
 struct FileIndex {
     index: HashMap<PathBuf, SystemTime>,
     location: PathBuf,
+    fs: Arc<dyn backend::Fs>,
 }
 
 impl FileIndex {
-    fn new(directory: PathBuf, files: &Option<Vec<String>>) -> Self {
+    fn new(directory: PathBuf, files: &Option<Vec<String>>, fs: Arc<dyn backend::Fs>) -> Self {
         let mut index: HashMap<PathBuf, SystemTime> = HashMap::new();
         if let Some(files) = files {
             for file in files {
@@ -158,54 +300,104 @@ impl FileIndex {
         Self {
             index,
             location: directory,
+            fs,
         }
     }
 
-    // Notify docs specify that there can be more than one file per event, however I haven't
-    // observed this. This currently only handles the first file per event, and will display number
-    // of events if > 1 event.
-    //
-    // i think making this async causes a race condition where order of event processing might get
-    // fucked up ? but idk lmao
-    async fn handle_event(&mut self, event: &Event) -> Option<PathBuf> {
-        let k = event.paths[0].to_str().expect("path is not valid unicode");
+    // strips everything before `location` off an absolute/relative notify path
+    // so index keys match what find_files_in produced
+    fn relativize(&self, path: &Path) -> PathBuf {
+        let k = path.to_str().expect("path is not valid unicode");
         let i = k
             .find(self.location.to_str().expect("path is not valid unicode"))
             .unwrap();
-        let k = PathBuf::from(&k[i..]);
+        PathBuf::from(&k[i..])
+    }
 
+    // i think making this async causes a race condition where order of event processing might get
+    // fucked up ? but idk lmao
+    async fn handle_event(&mut self, event: &Event) -> Vec<MirrorOp> {
+        if let EventKind::Modify(ModifyKind::Name(mode)) = event.kind {
+            return self.handle_rename(mode, event).await;
+        }
 
-        // was getting random events for files that dont exist here e.g. ./INBOX/4913
-        match event.kind {
+        let mut ops = Vec::new();
+        for path in &event.paths {
+            if let Some(op) = self.handle_path_event(path, event.kind).await {
+                ops.push(op);
+            }
+        }
+        ops
+    }
+
+    // was getting random events for files that dont exist here e.g. ./INBOX/4913
+    async fn handle_path_event(&mut self, path: &Path, kind: EventKind) -> Option<MirrorOp> {
+        let k = self.relativize(path);
+
+        match kind {
             EventKind::Create(kind) => {
-                if !k.exists() { return None }
                 if kind == CreateKind::Folder { return None }
 
-                let md = fs::metadata(&k).await.unwrap();
-                let v = md.modified().unwrap();
-                self.index.insert(k.clone(), v);
-                print!("[NEW] ");
+                let md = match self.fs.metadata(&k).await.unwrap() {
+                    Some(md) => md,
+                    None => return None,
+                };
+                self.index.insert(k.clone(), md.modified);
+                println!("[NEW] {file}", file = k.display());
+                Some(MirrorOp::Sync(k))
             }
             EventKind::Modify(_) => {
-                if !k.exists() || k.is_dir() { return None }
-
-                let md = fs::metadata(&k).await.unwrap();
-                let v = md.modified().unwrap();
-                self.index.insert(k.clone(), v);
-                print!("[MOD] ");
+                let md = match self.fs.metadata(&k).await.unwrap() {
+                    Some(md) if !md.is_dir => md,
+                    _ => return None,
+                };
+                self.index.insert(k.clone(), md.modified);
+                println!("[MOD] {file}", file = k.display());
+                Some(MirrorOp::Sync(k))
             }
             EventKind::Remove(kind) => {
                 if kind == RemoveKind::Folder { return None }
                 self.index.remove(&k);
-                print!("[DEL] ");
+                println!("[DEL] {file}", file = k.display());
+                Some(MirrorOp::Remove(k))
             }
-            _ => return None,
-        };
+            _ => None,
+        }
+    }
+
+    // notify pairs a move's old and new path together when it can (RenameMode::Both);
+    // when it can't, we just get told about one half and fall back to remove/create
+    async fn handle_rename(&mut self, mode: RenameMode, event: &Event) -> Vec<MirrorOp> {
+        match mode {
+            RenameMode::Both if event.paths.len() == 2 => {
+                let from = self.relativize(&event.paths[0]);
+                let to = self.relativize(&event.paths[1]);
 
-        let num_events = event.paths.len();
-        if num_events > 1 { print!("({num_events}) "); }
-        println!("{file}", file = k.display());
-        Some(k)
+                if let Some(modified) = self.index.remove(&from) {
+                    self.index.insert(to.clone(), modified);
+                }
+                println!("[REN] {from} -> {to}", from = from.display(), to = to.display());
+                vec![MirrorOp::Rename { from, to }]
+            }
+            RenameMode::From => {
+                let path = self.relativize(&event.paths[0]);
+                self.index.remove(&path);
+                println!("[DEL] {file}", file = path.display());
+                vec![MirrorOp::Remove(path)]
+            }
+            RenameMode::To => {
+                let path = self.relativize(&event.paths[0]);
+                match self.fs.metadata(&path).await.unwrap() {
+                    Some(md) => {
+                        self.index.insert(path.clone(), md.modified);
+                        println!("[NEW] {file}", file = path.display());
+                        vec![MirrorOp::Sync(path)]
+                    }
+                    None => vec![],
+                }
+            }
+            _ => vec![],
+        }
     }
 
     fn print(&self) {
@@ -219,74 +411,261 @@ impl FileIndex {
     }
 }
 
-fn host_path_to_target(host: &PathBuf) -> PathBuf {
+// strips the configured inbox dir (not necessarily DEFAULT_INBOX - see -d)
+// off a host path and rejoins it under CLONE/
+fn host_path_to_target(host: &Path, inbox: &Path) -> PathBuf {
     let host = host.to_string_lossy();
-    let target = host.replace(DEFAULT_INBOX, DEFAULT_TARGET);
+    let inbox = inbox.to_string_lossy();
+    let target = host.replacen(inbox.as_ref(), DEFAULT_TARGET, 1);
 
     PathBuf::from(target)
 }
 
-// Tries to copy from -> to, path isn't found creates the path
-async fn copy_with_dir(from: &PathBuf, to: &PathBuf) {
-    let mut target_path = to.clone();
-    match fs::copy(&from, &to).await {
+// same idea as host_path_to_target, but rooted under whatever directory the
+// remote side handed us instead of CLONE/
+fn host_path_to_remote(host: &Path, inbox: &Path, remote_root: &Path) -> PathBuf {
+    let host = host.to_string_lossy();
+    let inbox = inbox.to_string_lossy();
+    let rel = host.replacen(inbox.as_ref(), "", 1);
+
+    remote_root.join(rel)
+}
+
+// Tries to copy from -> to, path isn't found creates the path. Copies into a
+// temp file in the same directory as `to` first and renames it over the
+// final path, so a reader of the target directory never sees a half-copied
+// file, and a crash mid-copy just leaves an orphaned temp file instead of a
+// truncated one.
+async fn copy_with_dir(fs: &dyn backend::Fs, from: &Path, to: &Path) -> std::io::Result<()> {
+    let mut target_dir = to.to_path_buf();
+    target_dir.pop();
+
+    let tmp_name = format!(
+        ".{}.fm-tmp",
+        to.file_name().and_then(|n| n.to_str()).unwrap_or("mirror"),
+    );
+    let tmp_path = target_dir.join(tmp_name);
+
+    match fs.copy_file(from, &tmp_path).await {
         Ok(_) => {},
-        Err(err) if err.kind() == tokio::io::ErrorKind::NotFound => {
-            target_path.pop();
-            if let Err(err) = fs::create_dir_all(&target_path).await {
-                panic!("ERROR: couldn't crate path to {path}: {err}", path = target_path.display());
-            } else {
-                fs::copy(&from, &to).await.expect(format!("path to '{}' was constructed but isn't valid", to.display()).as_str());
-            }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            fs.create_dir_all(&target_dir).await?;
+            fs.copy_file(from, &tmp_path).await?;
         },
-        Err(err) => panic!("ERROR: idk: {err}"),
+        Err(err) => return Err(err),
     };
+
+    if let Err(err) = fs.rename(&tmp_path, to).await {
+        let _ = fs.remove_file(&tmp_path).await;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+// target for mirrored files: either the local CLONE dir (today's behavior,
+// driven through an `Fs` backend so it's swappable in tests) or a live
+// connection to a remote host speaking the ssh::Op protocol. Cheaply
+// Clone - Local just clones an Arc<dyn Fs> and concurrent copies can run
+// fully in parallel; Remote clones an Arc around the one shared connection,
+// which each mirror() call locks for as long as its remote op takes.
+#[derive(Clone)]
+enum MirrorTarget {
+    Local(Arc<dyn backend::Fs>),
+    Remote(Arc<tokio::sync::Mutex<ssh::Conn>>),
+}
+
+// sends from -> remote_to over the ssh connection, creating the remote
+// directory first if the write comes back NotFound (mirrors copy_with_dir)
+async fn ssh_copy_with_dir(conn: &mut ssh::Conn, from: &PathBuf, remote_to: &PathBuf) -> tokio::io::Result<()> {
+    let data = fs::read(from).await?;
+    match conn.send(ssh::Op::FileWrite { path: remote_to.clone(), data: data.clone() }).await? {
+        ssh::Reply::Ok => Ok(()),
+        ssh::Reply::NotFound => {
+            let mut dir = remote_to.clone();
+            dir.pop();
+            conn.send(ssh::Op::CreateDirAll { path: dir }).await?;
+            conn.send(ssh::Op::FileWrite { path: remote_to.clone(), data }).await?;
+            Ok(())
+        },
+        ssh::Reply::Err(err) => Err(tokio::io::Error::new(tokio::io::ErrorKind::Other, err)),
+        // FileWrite never gets this back, only Op::Metadata does
+        ssh::Reply::Modified(_) => unreachable!("FileWrite only replies Ok/NotFound/Err"),
+    }
 }
 
-// for now, just do async file i/o into clone dir, convert to doing it over ssh later
-// precondition: event is one of: new, remove, modify, event is on a file
-// initial files are already mirrored
-async fn mirror(host_file: &String, event: Option<&Event>) -> tokio::io::Result<()> {
-    let host_file = PathBuf::from(&host_file);
-    let target_path = host_path_to_target(&host_file);
-    let target_file = target_path.clone();
-
-
-    match event {
-        None => copy_with_dir(&host_file, &target_file).await,
-        Some(event) => {
-            match event.kind {
-                EventKind::Create(_) => {
-                    copy_with_dir(&host_file, &target_file).await;
+// replays a single index change onto the mirror target. Only locks the
+// remote connection for the duration of its own sends - local copies hold
+// no lock at all, so up to `semaphore`'s limit of them can run at once.
+async fn mirror(
+    op: &MirrorOp,
+    target: &MirrorTarget,
+    inbox: &Path,
+    semaphore: &Arc<tokio::sync::Semaphore>,
+) -> tokio::io::Result<()> {
+    // held for the whole call so total in-flight copies stay bounded across
+    // both the initial bulk clone and the live event loop
+    let _permit = semaphore.clone().acquire_owned().await.unwrap();
+
+    match target {
+        MirrorTarget::Local(fs) => {
+            match op {
+                MirrorOp::Sync(host_file) => {
+                    let target_file = host_path_to_target(host_file, inbox);
+                    copy_with_dir(fs.as_ref(), host_file, &target_file).await?;
                 },
-                EventKind::Modify(_) => {
-                    if host_file.is_file() {
-                        fs::copy(host_file, target_file).await?;
-                    }
+                MirrorOp::Remove(host_file) => {
+                    let target_file = host_path_to_target(host_file, inbox);
+                    fs.remove_file(&target_file).await?;
                 },
-                EventKind::Remove(_) => {
-                    fs::remove_file(target_file).await?;
+                MirrorOp::Rename { from, to } => {
+                    let target_from = host_path_to_target(from, inbox);
+                    let target_to = host_path_to_target(to, inbox);
+                    fs.rename(&target_from, &target_to).await?;
                 },
-                _ => panic!("Passed invalid event to mirror"),
-            }
+            };
         },
-    };
+        MirrorTarget::Remote(conn) => {
+            let mut conn = conn.lock().await;
 
+            match op {
+                MirrorOp::Sync(host_file) => {
+                    let remote_file = host_path_to_remote(host_file, inbox, &conn.remote_root);
+                    ssh_copy_with_dir(&mut conn, host_file, &remote_file).await?;
+                },
+                MirrorOp::Remove(host_file) => {
+                    let remote_file = host_path_to_remote(host_file, inbox, &conn.remote_root);
+                    conn.send(ssh::Op::RemoveFile { path: remote_file }).await?;
+                },
+                MirrorOp::Rename { from, to } => {
+                    // no rename op in the wire protocol yet, replay as remove+recopy
+                    let remote_from = host_path_to_remote(from, inbox, &conn.remote_root);
+                    conn.send(ssh::Op::RemoveFile { path: remote_from }).await?;
+                    let remote_to = host_path_to_remote(to, inbox, &conn.remote_root);
+                    ssh_copy_with_dir(&mut conn, to, &remote_to).await?;
+                },
+            };
+        },
+    };
 
     Ok(())
 }
 
+// a notify event sitting in the debounce buffer, keyed by its first path
+struct PendingEvent {
+    kind: EventKind,
+    paths: Vec<PathBuf>,
+    seen: Instant,
+}
+
+// buffers a raw notify event by its first path, coalescing it with whatever's
+// already pending for that path: Create followed by Modify collapses down to
+// a single Create (copy_with_dir already does a full copy), and a Create
+// immediately followed by a Remove cancels out entirely - the file never
+// existed long enough to be worth mirroring.
+//
+// a rename is its own pairing (old path + new path) and never coalesces with
+// what's pending at its "from" path - editors write-then-rename constantly
+// (temp file -> final name), so if a Create is still sitting unflushed there
+// it's returned instead of being silently clobbered, and the caller needs to
+// flush it right away before the rename's target identity takes over that key.
+fn buffer_event(pending: &mut HashMap<PathBuf, PendingEvent>, event: Event) -> Option<PendingEvent> {
+    let key = match event.paths.first() {
+        Some(path) => path.clone(),
+        None => return None,
+    };
+
+    let is_rename = matches!(event.kind, EventKind::Modify(ModifyKind::Name(_)));
+    let existing = pending.get(&key).map(|p| p.kind);
+
+    match existing {
+        Some(EventKind::Create(_)) if !is_rename && matches!(event.kind, EventKind::Remove(_)) => {
+            pending.remove(&key);
+            None
+        }
+        Some(existing @ EventKind::Create(_)) if !is_rename && matches!(event.kind, EventKind::Modify(_)) => {
+            pending.insert(key, PendingEvent { kind: existing, paths: event.paths, seen: Instant::now() });
+            None
+        }
+        Some(_) if is_rename => {
+            let to_flush = pending.remove(&key);
+            pending.insert(key, PendingEvent { kind: event.kind, paths: event.paths, seen: Instant::now() });
+            to_flush
+        }
+        _ => {
+            pending.insert(key, PendingEvent { kind: event.kind, paths: event.paths, seen: Instant::now() });
+            None
+        }
+    }
+}
+
+// replays a buffered event through the index and out to the mirror target -
+// the common tail end of both the debounce ticker and a forced early flush
+async fn flush_pending(
+    pending_event: PendingEvent,
+    index: &mut FileIndex,
+    target: &MirrorTarget,
+    semaphore: &Arc<tokio::sync::Semaphore>,
+) {
+    let event = Event {
+        kind: pending_event.kind,
+        paths: pending_event.paths,
+        ..Default::default()
+    };
+
+    let inbox = index.location.clone();
+    for op in index.handle_event(&event).await {
+        // TODO: error handling here (dont panic)
+        // just log error and continue
+        if let Err(err) = mirror(&op, target, &inbox, semaphore).await {
+            eprintln!("Failed to mirror an event: {err}");
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<> {
+    // `fm --serve` is the remote half of the ssh transport (see ssh::Conn::connect),
+    // not a normal inbox-watching run - hand off to it before touching argv any further
+    if env::args().nth(1).as_deref() == Some("--serve") {
+        if let Err(e) = ssh::serve().await {
+            eprintln!("ERROR: serve failed: {e}");
+            exit(1);
+        }
+        return Ok(());
+    }
+
     let directory = init_inbox();
     let directory = PathBuf::from(directory);
+    let debounce = init_debounce();
+    let watcher_kind = init_watcher_kind();
+    let max_workers = init_max_workers();
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(max_workers));
     let files = find_files_in(&directory);
-    let mut index = FileIndex::new(directory.clone(), &files);
+    let fs: Arc<dyn backend::Fs> = Arc::new(backend::RealFs);
+    let mut index = FileIndex::new(directory.clone(), &files, fs.clone());
     index.print();
 
+    let target = match init_remote() {
+        Some(remote) => {
+            let conn = ssh::Conn::connect(&remote).await.unwrap_or_else(|e| {
+                panic!("ERROR: couldn't connect to remote target: {e}");
+            });
+            MirrorTarget::Remote(Arc::new(tokio::sync::Mutex::new(conn)))
+        },
+        None => MirrorTarget::Local(fs.clone()),
+    };
+
     // Clone initial files
     if let Some(files) = files {
-        let futures: FuturesUnordered<_> = (&files).into_iter().map(|f| mirror(&f, None)).collect();
+        let futures: FuturesUnordered<_> = (&files).into_iter().map(|f| {
+            let target = target.clone();
+            let semaphore = semaphore.clone();
+            let directory = directory.clone();
+            async move {
+                mirror(&MirrorOp::Sync(PathBuf::from(f)), &target, &directory, &semaphore).await
+            }
+        }).collect();
         let res: Vec<_> = futures.collect::<Vec<_>>().await;
         for i in res {
             if let Err(e) = i {
@@ -299,11 +678,28 @@ async fn main() -> Result<> {
     // Start task to monitor files
     let (tx, mut rx) = tokio::sync::mpsc::channel(10);
     let task = tokio::task::spawn(async move {
-        let mut watcher = notify::recommended_watcher(move |event| {
-            tx.blocking_send(event)
-                .expect("couldn't send event over channel");
-        })
-        .unwrap();
+        let mut watcher: Box<dyn Watcher + Send> = match watcher_kind {
+            WatcherKind::Native => Box::new(
+                RecommendedWatcher::new(
+                    move |event| {
+                        tx.blocking_send(event)
+                            .expect("couldn't send event over channel");
+                    },
+                    Config::default(),
+                )
+                .unwrap(),
+            ),
+            WatcherKind::Poll(interval) => Box::new(
+                PollWatcher::new(
+                    move |event| {
+                        tx.blocking_send(event)
+                            .expect("couldn't send event over channel");
+                    },
+                    Config::default().with_poll_interval(interval),
+                )
+                .unwrap(),
+            ),
+        };
         // TODO: handle error, watch all available paths
         let res = watcher.watch(&directory, RecursiveMode::Recursive);
 
@@ -313,6 +709,11 @@ async fn main() -> Result<> {
             // find directories in {directory}, try to watch those instead
         }
 
+        let mut pending: HashMap<PathBuf, PendingEvent> = HashMap::new();
+        // wakes up often enough to flush a path ~on time without spinning;
+        // debounce windows shorter than this just flush on the next tick
+        let mut ticker = tokio::time::interval(Duration::from_millis(10));
+
         loop {
             select! {
                 _ = signal::ctrl_c() => {
@@ -321,17 +722,23 @@ async fn main() -> Result<> {
                 event = rx.recv() => {
                     if let Some(x) = event {
                         let x = x.unwrap();
-                        let path = index.handle_event(&x).await;
-                        if let Some(p) = path {
-                            // TODO: error handling here (dont panic)
-                            // just log error and continue
-                            match mirror(&p.to_string_lossy().to_string(), Some(&x)).await {
-                                Err(err) => { eprintln!("Failed to mirror {path}: {err}", path = p.display()) },
-                                Ok(_) => {},
-                            };
+                        if let Some(to_flush) = buffer_event(&mut pending, x) {
+                            flush_pending(to_flush, &mut index, &target, &semaphore).await;
                         }
                     }
                 }
+                _ = ticker.tick() => {
+                    let now = Instant::now();
+                    let ready: Vec<PathBuf> = pending.iter()
+                        .filter(|(_, pending)| now.duration_since(pending.seen) >= debounce)
+                        .map(|(key, _)| key.clone())
+                        .collect();
+
+                    for key in ready {
+                        let pending_event = pending.remove(&key).unwrap();
+                        flush_pending(pending_event, &mut index, &target, &semaphore).await;
+                    }
+                }
             };
         }
 
@@ -343,3 +750,69 @@ async fn main() -> Result<> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn handle_event_create_syncs_and_indexes_the_file() {
+        let fake = backend::FakeFs::new();
+        let path = PathBuf::from("INBOX/a.txt");
+        fake.seed_file(path.clone(), b"hi".to_vec(), SystemTime::now());
+        let fs: Arc<dyn backend::Fs> = Arc::new(fake);
+        let mut index = FileIndex::new(PathBuf::from("INBOX/"), &None, fs);
+
+        let event = Event {
+            kind: EventKind::Create(CreateKind::File),
+            paths: vec![path.clone()],
+            ..Default::default()
+        };
+
+        let ops = index.handle_event(&event).await;
+        assert!(matches!(ops.as_slice(), [MirrorOp::Sync(p)] if *p == path));
+        assert!(index.index.contains_key(&path));
+    }
+
+    #[tokio::test]
+    async fn handle_event_rename_both_moves_the_index_entry() {
+        let fake = backend::FakeFs::new();
+        let fs: Arc<dyn backend::Fs> = Arc::new(fake);
+        let mut index = FileIndex::new(PathBuf::from("INBOX/"), &None, fs);
+        let from = PathBuf::from("INBOX/.a.txt.tmp");
+        let to = PathBuf::from("INBOX/a.txt");
+        index.index.insert(from.clone(), SystemTime::now());
+
+        let event = Event {
+            kind: EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            paths: vec![from.clone(), to.clone()],
+            ..Default::default()
+        };
+
+        let ops = index.handle_event(&event).await;
+        assert!(matches!(ops.as_slice(), [MirrorOp::Rename { from: f, to: t }] if *f == from && *t == to));
+        assert!(!index.index.contains_key(&from));
+        assert!(index.index.contains_key(&to));
+    }
+
+    #[tokio::test]
+    async fn buffer_event_flushes_pending_create_before_same_path_rename() {
+        let mut pending = HashMap::new();
+        let tmp = PathBuf::from("INBOX/.a.txt.tmp");
+        let create = Event {
+            kind: EventKind::Create(CreateKind::File),
+            paths: vec![tmp.clone()],
+            ..Default::default()
+        };
+        assert!(buffer_event(&mut pending, create).is_none());
+
+        let rename = Event {
+            kind: EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            paths: vec![tmp.clone(), PathBuf::from("INBOX/a.txt")],
+            ..Default::default()
+        };
+        let flushed = buffer_event(&mut pending, rename).expect("pending create should be flushed");
+        assert!(matches!(flushed.kind, EventKind::Create(_)));
+        assert_eq!(flushed.paths, vec![tmp]);
+    }
+}
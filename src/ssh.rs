@@ -0,0 +1,221 @@
+// remote side of mirror: a tiny request/response protocol so CLONE can live on
+// another host. modeled loosely on distant's handler - we just need enough ops
+// to replay what handle_event already tells us happened locally.
+use std::env;
+use std::path::PathBuf;
+
+use russh::client;
+use russh::keys::key;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Op {
+    FileWrite { path: PathBuf, data: Vec<u8> },
+    FileAppend { path: PathBuf, data: Vec<u8> },
+    RemoveFile { path: PathBuf },
+    CreateDirAll { path: PathBuf },
+    Metadata { path: PathBuf },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Reply {
+    Ok,
+    NotFound,
+    Modified(std::time::SystemTime),
+    Err(String),
+}
+
+// target spec like `user@host:/path/to/CLONE`
+pub struct Target {
+    pub user: String,
+    pub host: String,
+    pub path: PathBuf,
+}
+
+pub fn parse_target(spec: &str) -> Option<Target> {
+    let (user, rest) = spec.split_once('@')?;
+    let (host, path) = rest.split_once(':')?;
+
+    Some(Target {
+        user: user.to_string(),
+        host: host.to_string(),
+        path: PathBuf::from(path),
+    })
+}
+
+// the standard `~/.ssh/known_hosts` - reused so `fm` pins host keys the same
+// way the system ssh client does
+fn known_hosts_path() -> PathBuf {
+    let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".ssh").join("known_hosts")
+}
+
+struct Handler {
+    host: String,
+    port: u16,
+}
+
+#[async_trait::async_trait]
+impl client::Handler for Handler {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, key: &key::PublicKey) -> Result<bool, Self::Error> {
+        let known_hosts = known_hosts_path();
+
+        match russh::keys::check_known_hosts_path(&self.host, self.port, key, &known_hosts) {
+            // key matches what's already pinned for this host
+            Ok(true) => Ok(true),
+            // known_hosts exists but has no entry matching this key for this
+            // host - reject, whether the host is new or its key changed
+            Ok(false) if known_hosts.exists() => Ok(false),
+            // no known_hosts file at all yet: learn the key (TOFU) instead of
+            // rejecting outright
+            Ok(false) => {
+                if let Some(parent) = known_hosts.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                match russh::keys::learn_known_hosts_path(&self.host, self.port, key, &known_hosts) {
+                    Ok(()) => Ok(true),
+                    Err(err) => {
+                        eprintln!("WARN: couldn't record host key for {}: {err}", self.host);
+                        Ok(true)
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("WARN: couldn't check known_hosts for {}: {err}", self.host);
+                Ok(false)
+            }
+        }
+    }
+}
+
+// a persistent ssh session plus the one channel we shell out through. `fm`
+// drives a tiny agent on the other end (`fm --serve`) over stdin/stdout and
+// talks the Op/Reply protocol above across it.
+pub struct Conn {
+    channel: russh::Channel<client::Msg>,
+    pub remote_root: PathBuf,
+}
+
+impl Conn {
+    pub async fn connect(target: &Target) -> std::io::Result<Self> {
+        let config = std::sync::Arc::new(client::Config::default());
+        let handler = Handler { host: target.host.clone(), port: 22 };
+        let mut session = client::connect(config, (target.host.as_str(), 22), handler)
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+
+        session
+            .authenticate_keyboard_interactive_start(&target.user, None)
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+
+        let channel = session
+            .channel_open_session()
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+
+        channel
+            .exec(true, "fm --serve")
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+
+        Ok(Self {
+            channel,
+            remote_root: target.path.clone(),
+        })
+    }
+
+    pub async fn send(&mut self, op: Op) -> std::io::Result<Reply> {
+        let encoded = bincode::serialize(&op)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        let mut frame = (encoded.len() as u32).to_be_bytes().to_vec();
+        frame.extend_from_slice(&encoded);
+
+        self.channel
+            .data(frame.as_slice())
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+
+        let mut len_buf = [0u8; 4];
+        self.channel
+            .make_reader()
+            .read_exact(&mut len_buf)
+            .await?;
+        let len = u32::from_be_bytes(len_buf) as usize;
+
+        let mut buf = vec![0u8; len];
+        self.channel.make_reader().read_exact(&mut buf).await?;
+
+        bincode::deserialize(&buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+// the other half of Conn: run as `fm --serve` on the remote host, read
+// length-prefixed Op frames off stdin, apply them to the real local disk,
+// and write a length-prefixed Reply back on stdout for each one.
+pub async fn serve() -> std::io::Result<()> {
+    let mut stdin = tokio::io::stdin();
+    let mut stdout = tokio::io::stdout();
+
+    loop {
+        let mut len_buf = [0u8; 4];
+        if stdin.read_exact(&mut len_buf).await.is_err() {
+            break; // EOF: the other end hung up
+        }
+        let len = u32::from_be_bytes(len_buf) as usize;
+
+        let mut buf = vec![0u8; len];
+        stdin.read_exact(&mut buf).await?;
+        let op: Op = bincode::deserialize(&buf)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        let reply = apply(op).await;
+
+        let encoded = bincode::serialize(&reply)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let mut frame = (encoded.len() as u32).to_be_bytes().to_vec();
+        frame.extend_from_slice(&encoded);
+
+        stdout.write_all(&frame).await?;
+        stdout.flush().await?;
+    }
+
+    Ok(())
+}
+
+async fn apply(op: Op) -> Reply {
+    if let Op::Metadata { path } = op {
+        return match tokio::fs::metadata(&path).await {
+            Ok(md) => match md.modified() {
+                Ok(modified) => Reply::Modified(modified),
+                Err(err) => Reply::Err(err.to_string()),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Reply::NotFound,
+            Err(err) => Reply::Err(err.to_string()),
+        };
+    }
+
+    let result = match op {
+        Op::FileWrite { path, data } => tokio::fs::write(&path, &data).await,
+        Op::FileAppend { path, data } => {
+            use tokio::io::AsyncWriteExt as _;
+            match tokio::fs::OpenOptions::new().append(true).open(&path).await {
+                Ok(mut file) => file.write_all(&data).await,
+                Err(err) => Err(err),
+            }
+        },
+        Op::RemoveFile { path } => tokio::fs::remove_file(&path).await,
+        Op::CreateDirAll { path } => tokio::fs::create_dir_all(&path).await,
+        Op::Metadata { .. } => unreachable!("handled above"),
+    };
+
+    match result {
+        Ok(_) => Reply::Ok,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Reply::NotFound,
+        Err(err) => Reply::Err(err.to_string()),
+    }
+}